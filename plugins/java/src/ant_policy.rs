@@ -1,16 +1,27 @@
 //! Ant student file policy
 
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tmc_langs_framework::policy::StudentFilePolicy;
+use tmc_langs_util::file_util::{Fs, IgnoreList, RealFs};
 
 pub struct AntStudentFilePolicy {
     config_file_parent_path: PathBuf,
+    ignore: IgnoreList,
 }
 
 impl AntStudentFilePolicy {
     pub fn new(config_file_parent_path: PathBuf) -> Self {
+        Self::new_with_fs(config_file_parent_path, Arc::new(RealFs))
+    }
+
+    /// Like `new`, but reads `.gitignore`/`.tmcignore` files through the given `Fs` rather than
+    /// the real filesystem, so the policy can be unit-tested against a `FakeFs`.
+    pub fn new_with_fs(config_file_parent_path: PathBuf, fs: Arc<dyn Fs>) -> Self {
+        let ignore = IgnoreList::new(fs, config_file_parent_path.clone());
         Self {
             config_file_parent_path,
+            ignore,
         }
     }
 }
@@ -18,6 +29,9 @@ impl AntStudentFilePolicy {
 impl StudentFilePolicy for AntStudentFilePolicy {
     fn is_student_source_file(&self, path: &Path) -> bool {
         path.starts_with("src")
+            && !self
+                .ignore
+                .is_ignored(&self.config_file_parent_path.join(path), false)
     }
 
     fn get_config_file_parent_path(&self) -> &Path {
@@ -28,6 +42,7 @@ impl StudentFilePolicy for AntStudentFilePolicy {
 #[cfg(test)]
 mod test {
     use super::*;
+    use tmc_langs_util::file_util::FakeFs;
 
     #[test]
     fn is_student_source_file() {
@@ -43,4 +58,27 @@ mod test {
         assert!(!policy.is_student_source_file(Path::new("dir/src/file")));
         assert!(!policy.is_student_source_file(Path::new("srca/file")));
     }
+
+    #[test]
+    fn is_not_student_source_file_when_tmcignored() {
+        let fs = FakeFs::new();
+        fs.insert_file(".tmcignore", "src/Generated.java\n");
+
+        let policy = AntStudentFilePolicy::new_with_fs(PathBuf::from(""), Arc::new(fs));
+        assert!(policy.is_student_source_file(Path::new("src/Main.java")));
+        assert!(!policy.is_student_source_file(Path::new("src/Generated.java")));
+    }
+
+    #[test]
+    fn is_not_student_source_file_when_tmcignored_with_absolute_root() {
+        let fs = FakeFs::new();
+        fs.insert_file("/exercises/exercise/.tmcignore", "src/Generated.java\n");
+
+        let policy = AntStudentFilePolicy::new_with_fs(
+            PathBuf::from("/exercises/exercise"),
+            Arc::new(fs),
+        );
+        assert!(policy.is_student_source_file(Path::new("src/Main.java")));
+        assert!(!policy.is_student_source_file(Path::new("src/Generated.java")));
+    }
 }
@@ -1,16 +1,27 @@
 //! Contains the language policy for the plugin.
 
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tmc_langs_framework::policy::StudentFilePolicy;
+use tmc_langs_util::file_util::{Fs, IgnoreList, RealFs};
 
 pub struct MakeStudentFilePolicy {
     config_file_parent_path: PathBuf,
+    ignore: IgnoreList,
 }
 
 impl MakeStudentFilePolicy {
     pub fn new(config_file_parent_path: PathBuf) -> Self {
+        Self::new_with_fs(config_file_parent_path, Arc::new(RealFs))
+    }
+
+    /// Like `new`, but reads `.gitignore`/`.tmcignore` files through the given `Fs` rather than
+    /// the real filesystem, so the policy can be unit-tested against a `FakeFs`.
+    pub fn new_with_fs(config_file_parent_path: PathBuf, fs: Arc<dyn Fs>) -> Self {
+        let ignore = IgnoreList::new(fs, config_file_parent_path.clone());
         Self {
             config_file_parent_path,
+            ignore,
         }
     }
 }
@@ -22,12 +33,16 @@ impl StudentFilePolicy for MakeStudentFilePolicy {
 
     fn is_student_source_file(&self, path: &Path) -> bool {
         path.starts_with("src")
+            && !self
+                .ignore
+                .is_ignored(&self.config_file_parent_path.join(path), false)
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use tmc_langs_util::file_util::FakeFs;
 
     #[test]
     fn is_student_source_file() {
@@ -43,4 +58,27 @@ mod test {
         assert!(!policy.is_student_source_file(Path::new("srcc")));
         assert!(!policy.is_student_source_file(Path::new("dir/src/file")));
     }
+
+    #[test]
+    fn is_not_student_source_file_when_tmcignored() {
+        let fs = FakeFs::new();
+        fs.insert_file(".tmcignore", "src/generated.o\n");
+
+        let policy = MakeStudentFilePolicy::new_with_fs(PathBuf::from(""), Arc::new(fs));
+        assert!(policy.is_student_source_file(Path::new("src/file")));
+        assert!(!policy.is_student_source_file(Path::new("src/generated.o")));
+    }
+
+    #[test]
+    fn is_not_student_source_file_when_tmcignored_with_absolute_root() {
+        let fs = FakeFs::new();
+        fs.insert_file("/exercises/exercise/.tmcignore", "src/generated.o\n");
+
+        let policy = MakeStudentFilePolicy::new_with_fs(
+            PathBuf::from("/exercises/exercise"),
+            Arc::new(fs),
+        );
+        assert!(policy.is_student_source_file(Path::new("src/file")));
+        assert!(!policy.is_student_source_file(Path::new("src/generated.o")));
+    }
 }
@@ -5,7 +5,8 @@ use fd_lock::FdLock;
 use std::fs::{self, File, ReadDir};
 use std::io::{Read, Write};
 use std::ops::{Deref, DerefMut};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tempfile::NamedTempFile;
 use walkdir::WalkDir;
 
@@ -57,6 +58,79 @@ mod lock_windows;
 #[cfg(windows)]
 pub use lock_windows::*;
 
+mod fake_fs;
+pub use fake_fs::FakeFs;
+
+mod ignore;
+pub use ignore::IgnoreList;
+
+/// A lock guard obtained through `Fs::open_file_lock`/`Fs::create_file_lock`. The lock is
+/// released when the guard is dropped.
+pub trait FsLockGuard {}
+
+impl FsLockGuard for FdLockWrapper {}
+
+/// Abstracts over the filesystem functions in this module so that code built on it, such as the
+/// `StudentFilePolicy` implementations (through `IgnoreList`), can be unit-tested against an
+/// in-memory `FakeFs` instead of paying for real disk IO in every test. `tar::unpack` and the
+/// other free functions here do not go through `Fs` and always use the real filesystem.
+pub trait Fs {
+    fn create_file(&self, path: &Path) -> Result<(), FileError>;
+    fn open_file(&self, path: &Path) -> Result<Box<dyn Read>, FileError>;
+    fn read_file(&self, path: &Path) -> Result<Vec<u8>, FileError>;
+    fn copy(&self, source: &Path, target: &Path) -> Result<(), FileError>;
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), FileError>;
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, FileError>;
+    fn remove_dir_all(&self, path: &Path) -> Result<(), FileError>;
+    fn open_file_lock(&self, path: &Path) -> Result<Box<dyn FsLockGuard>, FileError>;
+    fn create_file_lock(&self, path: &Path) -> Result<Box<dyn FsLockGuard>, FileError>;
+}
+
+/// Zero-cost `Fs` implementation delegating to the free functions in this module, i.e. the real
+/// filesystem.
+pub struct RealFs;
+
+impl Fs for RealFs {
+    fn create_file(&self, path: &Path) -> Result<(), FileError> {
+        create_file(path).map(|_| ())
+    }
+
+    fn open_file(&self, path: &Path) -> Result<Box<dyn Read>, FileError> {
+        open_file(path).map(|file| Box::new(file) as Box<dyn Read>)
+    }
+
+    fn read_file(&self, path: &Path) -> Result<Vec<u8>, FileError> {
+        read_file(path)
+    }
+
+    fn copy(&self, source: &Path, target: &Path) -> Result<(), FileError> {
+        copy(source, target)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), FileError> {
+        rename(from, to)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, FileError> {
+        Ok(read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<(), FileError> {
+        remove_dir_all(path)
+    }
+
+    fn open_file_lock(&self, path: &Path) -> Result<Box<dyn FsLockGuard>, FileError> {
+        Ok(Box::new(self::open_file_lock(path)?))
+    }
+
+    fn create_file_lock(&self, path: &Path) -> Result<Box<dyn FsLockGuard>, FileError> {
+        Ok(Box::new(self::create_file_lock(path)?))
+    }
+}
+
 pub fn temp_file() -> Result<File, FileError> {
     tempfile::tempfile().map_err(FileError::TempFile)
 }
@@ -163,6 +237,72 @@ pub fn read_to_file<R: Read, P: AsRef<Path>>(source: &mut R, target: P) -> Resul
     Ok(target_file)
 }
 
+/// Writes source into target atomically: the data is first written to a temporary file in
+/// target's directory, fsynced, and then renamed over target. Since the rename is a single
+/// syscall on the same filesystem, readers can never observe a partially written target, even if
+/// the process is killed or panics midway.
+///
+/// Note: creates all intermediary directories if needed.
+pub fn atomic_write_file<S: AsRef<[u8]>, P: AsRef<Path>>(
+    source: S,
+    target: P,
+) -> Result<(), FileError> {
+    let target = target.as_ref();
+    with_atomic_temp_file(target, |temp_file| {
+        temp_file
+            .write_all(source.as_ref())
+            .map_err(|e| FileError::FileWrite(target.to_path_buf(), e))
+    })
+}
+
+/// Reads all of the data from source and writes it into target atomically, see `atomic_write_file`.
+pub fn atomic_read_to_file<R: Read, P: AsRef<Path>>(
+    source: &mut R,
+    target: P,
+) -> Result<(), FileError> {
+    let target = target.as_ref();
+    with_atomic_temp_file(target, |temp_file| {
+        std::io::copy(source, temp_file)
+            .map_err(|e| FileError::FileWrite(target.to_path_buf(), e))?;
+        Ok(())
+    })
+}
+
+/// Creates a `NamedTempFile` next to `target` (so the final rename stays on the same
+/// filesystem), lets `write` fill it in, then fsyncs and renames it over `target`.
+/// If `target`'s parent directory doesn't exist yet, it's created and creating the temporary file
+/// is retried once.
+fn with_atomic_temp_file<P: AsRef<Path>>(
+    target: P,
+    write: impl Fn(&mut NamedTempFile) -> Result<(), FileError>,
+) -> Result<(), FileError> {
+    let target = target.as_ref();
+    let parent = target.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut temp_file = match NamedTempFile::new_in(parent) {
+        Ok(temp_file) => temp_file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            create_dir_all(parent)?;
+            NamedTempFile::new_in(parent).map_err(FileError::TempFile)?
+        }
+        Err(e) => return Err(FileError::TempFile(e)),
+    };
+
+    write(&mut temp_file)?;
+    temp_file
+        .as_file()
+        .sync_all()
+        .map_err(|e| FileError::FileWrite(target.to_path_buf(), e))?;
+    temp_file
+        .persist(target)
+        .map_err(|e| FileError::Rename {
+            from: e.file.path().to_path_buf(),
+            to: target.to_path_buf(),
+            source: e.error,
+        })?;
+    Ok(())
+}
+
 pub fn read_dir<P: AsRef<Path>>(path: P) -> Result<ReadDir, FileError> {
     fs::read_dir(&path).map_err(|e| FileError::DirRead(path.as_ref().to_path_buf(), e))
 }
@@ -193,6 +333,89 @@ pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<(), File
     })
 }
 
+/// Moves the file or directory at `source` into `target`, merging into an existing target
+/// directory tree rather than clobbering it.
+///
+/// `rename` alone isn't enough for this: it fails with `EXDEV` when `source` and `target` are on
+/// different filesystems, and when `target` is an existing directory it would need to replace it
+/// wholesale instead of merging into it. `move_files` instead recurses: for a directory, it
+/// creates missing destination subdirectories, removes destination entries that no longer exist
+/// in `source`, and for each source file either moves it over a new destination path or, if an
+/// existing destination file is byte-identical, leaves the destination file (and its mtime)
+/// untouched. This matters for incremental build tools that key off mtimes: re-deploying an
+/// unchanged exercise template over a previous extraction shouldn't bump every file's timestamp.
+pub fn move_files<P: AsRef<Path>, Q: AsRef<Path>>(source: P, target: Q) -> Result<(), FileError> {
+    let source = source.as_ref();
+    let target = target.as_ref();
+
+    if source.is_dir() {
+        move_dir(source, target)?;
+        remove_dir_empty(source).ok();
+        Ok(())
+    } else {
+        move_file(source, target)
+    }
+}
+
+fn move_dir(source: &Path, target: &Path) -> Result<(), FileError> {
+    if !target.exists() {
+        create_dir_all(target)?;
+    }
+
+    // drop destination entries that no longer exist in the source
+    for entry in read_dir(target)? {
+        let entry = entry.map_err(|e| FileError::DirRead(target.to_path_buf(), e))?;
+        let source_entry = source.join(entry.file_name());
+        if !source_entry.exists() {
+            if entry.path().is_dir() {
+                remove_dir_all(entry.path())?;
+            } else {
+                remove_file(entry.path())?;
+            }
+        }
+    }
+
+    for entry in read_dir(source)? {
+        let entry = entry.map_err(|e| FileError::DirRead(source.to_path_buf(), e))?;
+        let source_entry = entry.path();
+        let target_entry = target.join(entry.file_name());
+        if source_entry.is_dir() {
+            move_dir(&source_entry, &target_entry)?;
+            remove_dir_empty(&source_entry).ok();
+        } else {
+            move_file(&source_entry, &target_entry)?;
+        }
+    }
+    Ok(())
+}
+
+fn move_file(source: &Path, target: &Path) -> Result<(), FileError> {
+    if target.is_file() && files_are_identical(source, target)? {
+        // leave the existing destination file (and its mtime) alone
+        remove_file(source)?;
+        return Ok(());
+    }
+
+    if let Some(parent) = target.parent() {
+        create_dir_all(parent)?;
+    }
+    match rename(source, target) {
+        Ok(()) => Ok(()),
+        // rename fails with EXDEV when source and target are on different filesystems; any other
+        // error (permissions, a missing source, ...) should be reported rather than papered over
+        // by a copy that's likely to fail the same way.
+        Err(FileError::Rename { source: e, .. }) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            copy(source, target)?;
+            remove_file(source)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn files_are_identical(a: &Path, b: &Path) -> Result<bool, FileError> {
+    Ok(read_file(a)? == read_file(b)?)
+}
+
 /// Copies the file or directory at source into the target path.
 /// If the source is a file and the target is not a directory, the source file is copied to the target path.
 /// If the source is a file and the target is a directory, the source file is copied into the target directory.
@@ -267,10 +490,100 @@ pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(source: P, target: Q) -> Result<(),
     Ok(())
 }
 
+/// Like `copy`, but skips any source entry that `ignore` considers ignored (and, for an ignored
+/// directory, everything beneath it) instead of copying it into `target`. Used when packaging a
+/// submission so build artifacts, caches, and `.git` directories never end up in the archive.
+pub fn copy_filtered<P: AsRef<Path>, Q: AsRef<Path>>(
+    source: P,
+    target: Q,
+    ignore: &IgnoreList,
+) -> Result<(), FileError> {
+    let source = source.as_ref();
+    let target = target.as_ref();
+
+    if source.is_file() {
+        if ignore.is_ignored(source, false) {
+            return Ok(());
+        }
+        return copy(source, target);
+    }
+
+    if target.is_file() {
+        return Err(FileError::UnexpectedFile(target.to_path_buf()));
+    }
+
+    let prefix = source.parent().unwrap_or_else(|| Path::new(""));
+    for entry in WalkDir::new(source).into_iter().filter_entry(|entry| {
+        !ignore.is_ignored(entry.path(), entry.file_type().is_dir())
+    }) {
+        let entry = entry?;
+        let entry_path = entry.path();
+        let stripped = entry_path.strip_prefix(prefix).unwrap();
+        let target = target.join(stripped);
+        if entry_path.is_dir() {
+            create_dir_all(target)?;
+        } else {
+            if let Some(parent) = target.parent() {
+                create_dir_all(parent)?;
+            }
+            std::fs::copy(entry_path, &target).map_err(|e| FileError::FileCopy {
+                from: entry_path.to_path_buf(),
+                to: target.clone(),
+                source: e,
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Joins an archive entry path onto an extraction root without letting the entry escape it.
+///
+/// Archive entry paths come from untrusted submissions, so a naive `root.join(entry_path)` is
+/// vulnerable to tar-slip: an entry like `../../etc/passwd` or an absolute path can redirect the
+/// write outside of `root`. `Path::join`/`Path::components()` never resolve `..` against earlier
+/// components, so simply joining and then `strip_prefix`-checking the result doesn't catch this:
+/// `root.join("../../etc/passwd")` still has `root`'s components as a literal prefix. Instead,
+/// an entry with any `..` component is rejected with `FileError::PathEscape`, and of the rest
+/// only the `Normal` components are kept (dropping `RootDir`/`Prefix`, so an absolute entry lands
+/// relative to `root`, and dropping `CurDir`) before joining onto `root`. The `strip_prefix` check is kept as a
+/// belt-and-braces verification that the result still lives under `root`.
+pub fn join_safely<P: AsRef<Path>, Q: AsRef<Path>>(
+    root: P,
+    entry_path: Q,
+) -> Result<PathBuf, FileError> {
+    use std::path::Component;
+
+    let root = root.as_ref();
+    let entry_path = entry_path.as_ref();
+
+    if entry_path
+        .components()
+        .any(|component| matches!(component, Component::ParentDir))
+    {
+        return Err(FileError::PathEscape {
+            root: root.to_path_buf(),
+            entry: entry_path.to_path_buf(),
+        });
+    }
+
+    let relative: PathBuf = entry_path
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect();
+    let joined = root.join(relative);
+
+    joined
+        .strip_prefix(root)
+        .map_err(|_| FileError::PathEscape {
+            root: root.to_path_buf(),
+            entry: entry_path.to_path_buf(),
+        })?;
+    Ok(joined)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::path::PathBuf;
 
     fn init() {
         use log::*;
@@ -333,6 +646,138 @@ mod test {
         assert_eq!(conts, "file contents");
     }
 
+    #[test]
+    fn atomic_write_overwrites_existing_file() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        let target = file_to(&temp, "dir/file", "old contents");
+
+        atomic_write_file("new contents", &target).unwrap();
+
+        let conts = read_file_to_string(&target).unwrap();
+        assert_eq!(conts, "new contents");
+    }
+
+    #[test]
+    fn atomic_write_creates_missing_parent_dirs() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("missing/dir/file");
+
+        atomic_write_file("contents", &target).unwrap();
+
+        let conts = read_file_to_string(&target).unwrap();
+        assert_eq!(conts, "contents");
+    }
+
+    #[test]
+    fn move_files_merges_into_existing_target_dir() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        file_to(&temp, "src/new_file", "new contents");
+        dir_to(&temp, "target/keep_dir");
+        file_to(&temp, "target/stale_file", "should be removed");
+
+        move_files(temp.path().join("src"), temp.path().join("target")).unwrap();
+
+        assert_eq!(
+            read_file_to_string(temp.path().join("target/new_file")).unwrap(),
+            "new contents"
+        );
+        assert!(!temp.path().join("target/keep_dir").exists());
+        assert!(!temp.path().join("target/stale_file").exists());
+        assert!(!temp.path().join("src").exists());
+    }
+
+    #[test]
+    fn move_files_preserves_mtime_of_unchanged_file() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        file_to(&temp, "target/file", "same contents");
+        let original_mtime = std::fs::metadata(temp.path().join("target/file"))
+            .unwrap()
+            .modified()
+            .unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        file_to(&temp, "src/file", "same contents");
+
+        move_files(temp.path().join("src"), temp.path().join("target")).unwrap();
+
+        let new_mtime = std::fs::metadata(temp.path().join("target/file"))
+            .unwrap()
+            .modified()
+            .unwrap();
+        assert_eq!(original_mtime, new_mtime);
+    }
+
+    #[test]
+    fn move_files_overwrites_changed_file() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        file_to(&temp, "target/file", "old contents");
+        file_to(&temp, "src/file", "new contents");
+
+        move_files(temp.path().join("src"), temp.path().join("target")).unwrap();
+
+        assert_eq!(
+            read_file_to_string(temp.path().join("target/file")).unwrap(),
+            "new contents"
+        );
+    }
+
+    #[test]
+    fn move_files_propagates_non_exdev_rename_errors() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("missing_src");
+        let target = temp.path().join("target/file");
+
+        let error = move_files(&source, &target).unwrap_err();
+        assert!(matches!(error, FileError::Rename { .. }));
+    }
+
+    #[test]
+    fn copy_filtered_skips_ignored_files_and_dirs() {
+        init();
+
+        let temp = tempfile::tempdir().unwrap();
+        file_to(&temp, "dir/src/Main.java", "class Main {}");
+        file_to(&temp, "dir/build/Main.class", "binary junk");
+        std::fs::write(temp.path().join("dir/.gitignore"), "build/\n").unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        let ignore = IgnoreList::new(Arc::new(RealFs), temp.path().join("dir"));
+        copy_filtered(temp.path().join("dir"), target.path(), &ignore).unwrap();
+
+        assert!(target.path().join("dir/src/Main.java").is_file());
+        assert!(!target.path().join("dir/build").exists());
+    }
+
+    #[test]
+    fn join_safely_joins_normal_paths() {
+        let joined = join_safely("/root", "some/file").unwrap();
+        assert_eq!(joined, PathBuf::from("/root/some/file"));
+    }
+
+    #[test]
+    fn join_safely_strips_leading_slash() {
+        let joined = join_safely("/root", "/some/file").unwrap();
+        assert_eq!(joined, PathBuf::from("/root/some/file"));
+    }
+
+    #[test]
+    fn join_safely_rejects_path_escaping_root() {
+        let err = join_safely("/root", "../../etc/passwd").unwrap_err();
+        assert!(matches!(err, FileError::PathEscape { .. }));
+    }
+
     #[test]
     fn copies_dir() {
         init();
@@ -0,0 +1,213 @@
+//! An in-memory `Fs` implementation for deterministically testing code that goes through `Fs`,
+//! such as `StudentFilePolicy` implementations, without touching disk.
+
+use super::{Fs, FsLockGuard};
+use crate::error::FileError;
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+enum Node {
+    Dir,
+    File(Vec<u8>),
+}
+
+/// An in-memory filesystem backed by a `BTreeMap` from path to file/directory/lock state.
+#[derive(Default)]
+pub struct FakeFs {
+    nodes: Mutex<BTreeMap<PathBuf, Node>>,
+    locked: Arc<Mutex<BTreeMap<PathBuf, ()>>>,
+}
+
+impl FakeFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a file with the given contents, creating any missing parent directories.
+    /// Convenience for setting up fixtures in tests.
+    pub fn insert_file(&self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) {
+        let path = path.into();
+        let mut nodes = self.nodes.lock().unwrap();
+        Self::ensure_parents(&mut nodes, &path);
+        nodes.insert(path, Node::File(contents.into()));
+    }
+
+    fn ensure_parents(nodes: &mut BTreeMap<PathBuf, Node>, path: &Path) {
+        let mut ancestor = path.parent();
+        while let Some(dir) = ancestor {
+            if nodes.contains_key(dir) {
+                break;
+            }
+            nodes.insert(dir.to_path_buf(), Node::Dir);
+            ancestor = dir.parent();
+        }
+    }
+
+    fn not_found(path: &Path) -> FileError {
+        FileError::FileOpen(
+            path.to_path_buf(),
+            std::io::Error::from(std::io::ErrorKind::NotFound),
+        )
+    }
+}
+
+impl Fs for FakeFs {
+    fn create_file(&self, path: &Path) -> Result<(), FileError> {
+        let mut nodes = self.nodes.lock().unwrap();
+        Self::ensure_parents(&mut nodes, path);
+        nodes.insert(path.to_path_buf(), Node::File(vec![]));
+        Ok(())
+    }
+
+    fn open_file(&self, path: &Path) -> Result<Box<dyn std::io::Read>, FileError> {
+        let data = self.read_file(path)?;
+        Ok(Box::new(Cursor::new(data)))
+    }
+
+    fn read_file(&self, path: &Path) -> Result<Vec<u8>, FileError> {
+        let nodes = self.nodes.lock().unwrap();
+        match nodes.get(path) {
+            Some(Node::File(data)) => Ok(data.clone()),
+            _ => Err(Self::not_found(path)),
+        }
+    }
+
+    fn copy(&self, source: &Path, target: &Path) -> Result<(), FileError> {
+        let data = self.read_file(source)?;
+        let mut nodes = self.nodes.lock().unwrap();
+        Self::ensure_parents(&mut nodes, target);
+        nodes.insert(target.to_path_buf(), Node::File(data));
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), FileError> {
+        let mut nodes = self.nodes.lock().unwrap();
+        let node = nodes.remove(from).ok_or_else(|| Self::not_found(from))?;
+        Self::ensure_parents(&mut nodes, to);
+        nodes.insert(to.to_path_buf(), node);
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, FileError> {
+        let nodes = self.nodes.lock().unwrap();
+        Ok(nodes
+            .keys()
+            .filter(|candidate| candidate.parent() == Some(path))
+            .cloned()
+            .collect())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<(), FileError> {
+        let mut nodes = self.nodes.lock().unwrap();
+        nodes.retain(|candidate, _| candidate != path && !candidate.starts_with(path));
+        Ok(())
+    }
+
+    fn open_file_lock(&self, path: &Path) -> Result<Box<dyn FsLockGuard>, FileError> {
+        // mirrors RealFs::open_file_lock: the file must already exist, and its contents are left
+        // untouched.
+        self.read_file(path)?;
+        Ok(self.lock_guard(path))
+    }
+
+    fn create_file_lock(&self, path: &Path) -> Result<Box<dyn FsLockGuard>, FileError> {
+        // mirrors RealFs::create_file_lock: (re)creates the file, truncating any existing
+        // contents.
+        self.create_file(path)?;
+        Ok(self.lock_guard(path))
+    }
+}
+
+impl FakeFs {
+    fn lock_guard(&self, path: &Path) -> Box<dyn FsLockGuard> {
+        self.locked.lock().unwrap().insert(path.to_path_buf(), ());
+        Box::new(FakeFsLockGuard {
+            locked: Arc::clone(&self.locked),
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+struct FakeFsLockGuard {
+    locked: Arc<Mutex<BTreeMap<PathBuf, ()>>>,
+    path: PathBuf,
+}
+
+impl FsLockGuard for FakeFsLockGuard {}
+
+impl Drop for FakeFsLockGuard {
+    fn drop(&mut self) {
+        self.locked.lock().unwrap().remove(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn writes_and_reads_back_a_file() {
+        let fs = FakeFs::new();
+        fs.create_file(Path::new("dir/file")).unwrap();
+        let data = fs.read_file(Path::new("dir/file")).unwrap();
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn copy_duplicates_contents() {
+        let fs = FakeFs::new();
+        fs.insert_file("dir/file", "contents");
+        fs.copy(Path::new("dir/file"), Path::new("other/file"))
+            .unwrap();
+        let data = fs.read_file(Path::new("other/file")).unwrap();
+        assert_eq!(data, b"contents");
+    }
+
+    #[test]
+    fn remove_dir_all_drops_descendants() {
+        let fs = FakeFs::new();
+        fs.insert_file("dir/sub/file", "contents");
+        fs.remove_dir_all(Path::new("dir")).unwrap();
+        assert!(fs.read_file(Path::new("dir/sub/file")).is_err());
+    }
+
+    #[test]
+    fn lock_is_released_on_drop() {
+        let fs = FakeFs::new();
+        let guard = fs.create_file_lock(Path::new("dir/file")).unwrap();
+        assert!(fs.locked.lock().unwrap().contains_key(Path::new("dir/file")));
+        drop(guard);
+        assert!(!fs.locked.lock().unwrap().contains_key(Path::new("dir/file")));
+    }
+
+    #[test]
+    fn create_file_lock_truncates_existing_contents() {
+        let fs = FakeFs::new();
+        fs.insert_file("dir/file", "old contents");
+        fs.create_file_lock(Path::new("dir/file")).unwrap();
+        assert!(fs.read_file(Path::new("dir/file")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn create_file_lock_creates_a_missing_file() {
+        let fs = FakeFs::new();
+        fs.create_file_lock(Path::new("dir/file")).unwrap();
+        assert!(fs.read_file(Path::new("dir/file")).is_ok());
+    }
+
+    #[test]
+    fn open_file_lock_errors_on_a_missing_file() {
+        let fs = FakeFs::new();
+        assert!(fs.open_file_lock(Path::new("dir/file")).is_err());
+    }
+
+    #[test]
+    fn open_file_lock_leaves_existing_contents_untouched() {
+        let fs = FakeFs::new();
+        fs.insert_file("dir/file", "contents");
+        fs.open_file_lock(Path::new("dir/file")).unwrap();
+        assert_eq!(fs.read_file(Path::new("dir/file")).unwrap(), b"contents");
+    }
+}
@@ -0,0 +1,274 @@
+//! A small gitignore/`.tmcignore`-style matcher used to keep build artifacts, caches, and VCS
+//! directories out of packaged submissions.
+
+use super::Fs;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const IGNORE_FILE_NAMES: [&str; 2] = [".gitignore", ".tmcignore"];
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    /// The raw glob, without the leading `!`, leading `/`, or trailing `/`.
+    glob: String,
+    negated: bool,
+    /// Only matches directories.
+    dir_only: bool,
+    /// Anchored to the directory the pattern file was found in, rather than matching at any depth.
+    anchored: bool,
+}
+
+impl Pattern {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let negated = line.starts_with('!');
+        let line = if negated { &line[1..] } else { line };
+
+        let dir_only = line.ends_with('/');
+        let line = line.strip_suffix('/').unwrap_or(line);
+
+        let anchored = line.starts_with('/');
+        let glob = line.strip_prefix('/').unwrap_or(line).to_string();
+
+        if glob.is_empty() {
+            return None;
+        }
+        Some(Self {
+            glob,
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// `relative` is the path of the candidate relative to the directory this pattern was
+    /// loaded from, e.g. `target/debug/build` for a pattern loaded from the project root.
+    fn matches(&self, relative: &Path, is_dir: bool) -> bool {
+        let relative = relative.to_string_lossy();
+        let components: Vec<&str> = relative.split('/').collect();
+        if self.dir_only {
+            // A dir-only pattern matches the candidate itself only if it's a directory, but it
+            // also matches any file or directory nested inside a directory that matches, e.g.
+            // `target/` must still ignore `target/debug/build.log`.
+            let dir_components = if is_dir {
+                components.len()
+            } else {
+                components.len().saturating_sub(1)
+            };
+            if self.anchored {
+                dir_components > 0 && glob_match(&self.glob, &components[..dir_components].join("/"))
+            } else {
+                (0..dir_components).any(|start| glob_match(&self.glob, &components[start..dir_components].join("/")))
+            }
+        } else if self.anchored {
+            glob_match(&self.glob, &relative)
+        } else {
+            // An unanchored pattern may match the candidate's full relative path or any suffix
+            // starting at a path separator, mirroring gitignore's "matches at any depth" rule.
+            (0..components.len()).any(|start| glob_match(&self.glob, &components[start..].join("/")))
+        }
+    }
+}
+
+/// Matches a single glob segment/path against a pattern supporting `*` (any run of characters
+/// except `/`), `**` (any run of characters including `/`), and `?` (a single character other
+/// than `/`).
+fn glob_match(pattern: &str, candidate: &str) -> bool {
+    fn inner(pattern: &[u8], candidate: &[u8]) -> bool {
+        match (pattern.first(), candidate.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) if pattern.get(1) == Some(&b'*') => {
+                inner(&pattern[2..], candidate)
+                    || (!candidate.is_empty() && inner(pattern, &candidate[1..]))
+            }
+            (Some(b'*'), _) => {
+                inner(&pattern[1..], candidate)
+                    || (candidate.first().is_some_and(|c| *c != b'/')
+                        && inner(pattern, &candidate[1..]))
+            }
+            (Some(b'?'), Some(c)) if *c != b'/' => inner(&pattern[1..], &candidate[1..]),
+            (Some(p), Some(c)) if p == c => inner(&pattern[1..], &candidate[1..]),
+            _ => false,
+        }
+    }
+    inner(pattern.as_bytes(), candidate.as_bytes())
+}
+
+/// Collects and caches `.gitignore`/`.tmcignore` patterns walking from a file's directory up to
+/// a project root, so a directory's ignore files are only parsed once no matter how many files
+/// in it (or below it) are checked.
+///
+/// Ignore files are read through an `Fs`, rather than the real filesystem directly, so policies
+/// built on top of this can be unit-tested against a `FakeFs` instead of real temp directories.
+pub struct IgnoreList {
+    fs: Arc<dyn Fs>,
+    root: PathBuf,
+    /// Paths that should never be ignored, even if a pattern would otherwise match them.
+    includes: Vec<PathBuf>,
+    cache: Mutex<HashMap<PathBuf, Vec<Pattern>>>,
+}
+
+impl IgnoreList {
+    pub fn new(fs: Arc<dyn Fs>, root: impl Into<PathBuf>) -> Self {
+        Self {
+            fs,
+            root: root.into(),
+            includes: Vec::new(),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_includes(fs: Arc<dyn Fs>, root: impl Into<PathBuf>, includes: Vec<PathBuf>) -> Self {
+        Self {
+            fs,
+            root: root.into(),
+            includes,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns whether `path` should be excluded from packaging. `path` must start with the root
+    /// (e.g. `root.join(relative)`); a path outside the root is never ignored. Ancestor directories are checked from the root down to `path`'s own directory,
+    /// so a more specific `.gitignore` can re-include (`!pattern`) something an ancestor ignores.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self
+            .includes
+            .iter()
+            .any(|include| path == include || path.starts_with(include))
+        {
+            return false;
+        }
+
+        let mut ignored = false;
+        for dir in self.ancestor_dirs(path) {
+            let Ok(relative) = path.strip_prefix(&dir) else {
+                continue;
+            };
+            for pattern in self.patterns_for_dir(&dir) {
+                if pattern.matches(relative, is_dir) {
+                    ignored = !pattern.negated;
+                }
+            }
+        }
+        ignored
+    }
+
+    /// `root`, then each directory between `root` and `path`'s parent, in that order, so patterns
+    /// closer to `path` are considered (and applied) last.
+    fn ancestor_dirs(&self, path: &Path) -> Vec<PathBuf> {
+        let mut dirs = vec![self.root.clone()];
+        if let Ok(relative) = path.strip_prefix(&self.root) {
+            let mut current = self.root.clone();
+            if let Some(parent) = relative.parent() {
+                for component in parent.components() {
+                    current = current.join(component);
+                    dirs.push(current.clone());
+                }
+            }
+        }
+        dirs
+    }
+
+    fn patterns_for_dir(&self, dir: &Path) -> Vec<Pattern> {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(patterns) = cache.get(dir) {
+            return patterns.clone();
+        }
+
+        let mut patterns = vec![];
+        for file_name in IGNORE_FILE_NAMES {
+            let ignore_file = dir.join(file_name);
+            if let Ok(contents) = self.fs.read_file(&ignore_file) {
+                let contents = String::from_utf8_lossy(&contents);
+                patterns.extend(contents.lines().filter_map(Pattern::parse));
+            }
+        }
+        cache.insert(dir.to_path_buf(), patterns.clone());
+        patterns
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::FakeFs;
+    use super::*;
+
+    #[test]
+    fn ignores_matching_file() {
+        let fs = FakeFs::new();
+        fs.insert_file("/project/.gitignore", "*.class\n");
+
+        let ignore = IgnoreList::new(Arc::new(fs), "/project");
+        assert!(ignore.is_ignored(Path::new("/project/Main.class"), false));
+        assert!(!ignore.is_ignored(Path::new("/project/Main.java"), false));
+    }
+
+    #[test]
+    fn dir_only_pattern_does_not_match_files() {
+        let fs = FakeFs::new();
+        fs.insert_file("/project/.gitignore", "target/\n");
+
+        let ignore = IgnoreList::new(Arc::new(fs), "/project");
+        assert!(ignore.is_ignored(Path::new("/project/target"), true));
+        assert!(!ignore.is_ignored(Path::new("/project/target"), false));
+    }
+
+    #[test]
+    fn dir_only_pattern_matches_files_nested_inside_the_directory() {
+        let fs = FakeFs::new();
+        fs.insert_file("/project/.gitignore", "build/\n");
+
+        let ignore = IgnoreList::new(Arc::new(fs), "/project");
+        assert!(ignore.is_ignored(Path::new("/project/src/build/Main.class"), false));
+        assert!(!ignore.is_ignored(Path::new("/project/src/Main.class"), false));
+    }
+
+    #[test]
+    fn anchored_pattern_only_matches_at_root() {
+        let fs = FakeFs::new();
+        fs.insert_file("/project/.gitignore", "/build\n");
+
+        let ignore = IgnoreList::new(Arc::new(fs), "/project");
+        assert!(ignore.is_ignored(Path::new("/project/build"), true));
+        assert!(!ignore.is_ignored(Path::new("/project/src/build"), true));
+    }
+
+    #[test]
+    fn negation_reincludes_a_file() {
+        let fs = FakeFs::new();
+        fs.insert_file("/project/.gitignore", "*.class\n!Keep.class\n");
+
+        let ignore = IgnoreList::new(Arc::new(fs), "/project");
+        assert!(ignore.is_ignored(Path::new("/project/Main.class"), false));
+        assert!(!ignore.is_ignored(Path::new("/project/Keep.class"), false));
+    }
+
+    #[test]
+    fn explicit_include_overrides_ignore_rule() {
+        let fs = FakeFs::new();
+        fs.insert_file("/project/.gitignore", "*.class\n");
+
+        let ignore = IgnoreList::with_includes(
+            Arc::new(fs),
+            "/project",
+            vec![PathBuf::from("/project/Main.class")],
+        );
+        assert!(!ignore.is_ignored(Path::new("/project/Main.class"), false));
+    }
+
+    #[test]
+    fn nested_gitignore_is_only_parsed_once_per_directory() {
+        let fs = FakeFs::new();
+        fs.insert_file("/project/sub/.gitignore", "*.tmp\n");
+
+        let ignore = IgnoreList::new(Arc::new(fs), "/project");
+        assert!(ignore.is_ignored(Path::new("/project/sub/a.tmp"), false));
+        assert!(ignore.is_ignored(Path::new("/project/sub/b.tmp"), false));
+        assert_eq!(ignore.cache.lock().unwrap().len(), 2);
+    }
+}
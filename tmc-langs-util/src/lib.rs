@@ -1,3 +1,4 @@
+pub mod file_util;
 pub mod tar;
 pub mod task_executor;
 
@@ -0,0 +1,52 @@
+//! Packs and unpacks the `.tar` archives used to ship and submit exercises.
+
+use crate::error::FileError;
+use crate::file_util;
+use std::io::Read;
+use std::path::Path;
+use tar::{Archive, EntryType};
+
+/// Unpacks `archive` into `target`, one entry at a time.
+///
+/// Entry paths come straight off the wire from a student submission, so they can't be trusted:
+/// an entry like `../../etc/passwd` or an absolute path is a tar-slip attempt to write outside of
+/// `target`. Every entry is joined onto `target` with `file_util::join_safely`, which rejects
+/// anything that would land outside of it.
+///
+/// Entries are handled according to their type: directory entries create a directory rather than
+/// an empty file (ordinary archives built with e.g. `append_dir_all` include these, and the next
+/// entry nested under one would otherwise fail to extract because its parent is a plain file, not
+/// a directory); symlink and hardlink entries are skipped outright, since materializing them
+/// could itself be used to redirect a later entry's write outside of `target`.
+pub fn unpack<R: Read>(archive: R, target: &Path) -> Result<(), FileError> {
+    let mut archive = Archive::new(archive);
+    for entry in archive.entries().map_err(FileError::TarRead)? {
+        let mut entry = entry.map_err(FileError::TarRead)?;
+        let entry_path = entry.path().map_err(FileError::TarRead)?.to_path_buf();
+        let target_path = file_util::join_safely(target, &entry_path)?;
+        let entry_type = entry.header().entry_type();
+
+        match entry_type {
+            EntryType::Symlink | EntryType::Link => {
+                log::warn!(
+                    "skipping {} entry {}",
+                    if entry_type == EntryType::Symlink { "symlink" } else { "hardlink" },
+                    entry_path.display()
+                );
+                continue;
+            }
+            EntryType::Directory => {
+                log::debug!("unpacking dir {} -> {}", entry_path.display(), target_path.display());
+                file_util::create_dir_all(&target_path)?;
+            }
+            _ => {
+                log::debug!("unpacking {} -> {}", entry_path.display(), target_path.display());
+                if let Some(parent) = target_path.parent() {
+                    file_util::create_dir_all(parent)?;
+                }
+                file_util::atomic_read_to_file(&mut entry, &target_path)?;
+            }
+        }
+    }
+    Ok(())
+}